@@ -1,7 +1,8 @@
 use std::fmt::{self, Debug, Display};
 use std::hash::Hash;
-use std::iter;
-use std::ops::{Add, Sub};
+use std::marker::PhantomData;
+use std::ops::{Add, Index, Sub};
+use std::{iter, slice};
 
 use enum_as_inner::EnumAsInner;
 use itertools::Itertools;
@@ -9,8 +10,92 @@ use strum::{Display, EnumString, IntoStaticStr};
 
 use crate::Ref;
 
+/// A handle into an [`ExprArena`]. Recursive positions in [`Expr`] hold these
+/// instead of owning their children, so a subtree can be cloned in O(1) by
+/// copying the id and node identities stay stable across passes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ExprId(u32);
+
+impl ExprId {
+    #[inline]
+    fn into_raw(self) -> u32 {
+        self.0
+    }
+
+    #[inline]
+    fn from_raw(raw: u32) -> Self {
+        ExprId(raw)
+    }
+}
+
+/// The open functor underlying [`Expr`]: structurally identical to the AST,
+/// but with every recursive child position abstracted into the type parameter
+/// `A`. Instantiating `A = ExprId` recovers an arena node ([`Expr`]); folding a
+/// body over the arena instantiates `A` with the fold result so transforms can
+/// be written as `ExprF` algebras instead of hand-rolled recursion.
 #[derive(Debug, EnumAsInner)]
-pub enum Expr<Name: NameKind>
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound(
+        serialize = "A: serde::Serialize, Name::Reference: serde::Serialize, Name::Callable: \
+                     serde::Serialize, Name::Local: serde::Serialize, Name::Function: serde::Serialize, \
+                     Name::Member: serde::Serialize, Name::Type: serde::Serialize",
+        deserialize = "A: serde::Deserialize<'de>, Name::Reference: serde::Deserialize<'de>, \
+                       Name::Callable: serde::Deserialize<'de>, Name::Local: serde::Deserialize<'de>, \
+                       Name::Function: serde::Deserialize<'de>, Name::Member: serde::Deserialize<'de>, \
+                       Name::Type: serde::Deserialize<'de>"
+    ))
+)]
+pub enum ExprF<Name, A>
+where
+    Name: NameKind,
+    Name::Reference: Debug,
+    Name::Callable: Debug,
+    Name::Local: Debug,
+    Name::Function: Debug,
+    Name::Member: Debug,
+    Name::Type: Debug,
+{
+    Ident(Name::Reference),
+    Constant(Constant),
+    ArrayLit(Vec<A>, Option<Name::Type>),
+    InterpolatedString(Ref<str>, Vec<(A, Ref<str>)>),
+    Declare(Name::Local, Option<Name::Type>, Option<A>),
+    Cast(Name::Type, A),
+    Assign(A, A),
+    Call(Name::Callable, Vec<Name::Type>, Vec<A>),
+    MethodCall(A, Name::Function, Vec<A>),
+    Member(A, Name::Member),
+    ArrayElem(A, A),
+    New(Name::Type, Vec<A>),
+    Return(Option<A>),
+    Seq(SeqF<A>),
+    Switch(A, Vec<SwitchCaseF<A>>, Option<SeqF<A>>),
+    Goto(Target),
+    If(A, SeqF<A>, Option<SeqF<A>>),
+    Conditional(A, A, A),
+    While(A, SeqF<A>),
+    ForIn(Name::Local, A, SeqF<A>),
+    BinOp(A, A, BinOp),
+    UnOp(A, UnOp),
+    This,
+    Super,
+    Break,
+    Null,
+}
+
+/// An AST node as stored in an [`ExprArena`]: the functor with its children
+/// given as arena handles.
+pub type Expr<Name> = ExprF<Name, ExprId>;
+
+/// An annotated AST: each node carries a user-chosen annotation `A` and its
+/// span alongside the [`ExprF`] layer, whose children are further annotated
+/// nodes. This gives passes a place to hang inferred types, resolved scope ids,
+/// or diagnostics without mutating or duplicating the core [`Expr`] enum;
+/// [`Annotated::strip`] lowers the tree back into an arena.
+pub struct Annotated<Name, A>
 where
     Name: NameKind,
     Name::Reference: Debug,
@@ -20,32 +105,40 @@ where
     Name::Member: Debug,
     Name::Type: Debug,
 {
-    Ident(Name::Reference, Span),
-    Constant(Constant, Span),
-    ArrayLit(Vec<Self>, Option<Name::Type>, Span),
-    InterpolatedString(Ref<str>, Vec<(Self, Ref<str>)>, Span),
-    Declare(Name::Local, Option<Name::Type>, Option<Box<Self>>, Span),
-    Cast(Name::Type, Box<Self>, Span),
-    Assign(Box<Self>, Box<Self>, Span),
-    Call(Name::Callable, Vec<Name::Type>, Vec<Self>, Span),
-    MethodCall(Box<Self>, Name::Function, Vec<Self>, Span),
-    Member(Box<Self>, Name::Member, Span),
-    ArrayElem(Box<Self>, Box<Self>, Span),
-    New(Name::Type, Vec<Self>, Span),
-    Return(Option<Box<Self>>, Span),
-    Seq(Seq<Name>),
-    Switch(Box<Self>, Vec<SwitchCase<Name>>, Option<Seq<Name>>, Span),
-    Goto(Target, Span),
-    If(Box<Self>, Seq<Name>, Option<Seq<Name>>, Span),
-    Conditional(Box<Self>, Box<Self>, Box<Self>, Span),
-    While(Box<Self>, Seq<Name>, Span),
-    ForIn(Name::Local, Box<Self>, Seq<Name>, Span),
-    BinOp(Box<Self>, Box<Self>, BinOp, Span),
-    UnOp(Box<Self>, UnOp, Span),
-    This(Span),
-    Super(Span),
-    Break(Span),
-    Null(Span),
+    pub annotation: A,
+    pub span: Span,
+    pub expr: ExprF<Name, Box<Annotated<Name, A>>>,
+}
+
+impl<N, A> Annotated<N, A>
+where
+    N: NameKind,
+    N::Reference: Debug,
+    N::Callable: Debug,
+    N::Local: Debug,
+    N::Function: Debug,
+    N::Member: Debug,
+    N::Type: Debug,
+{
+    /// Rebuilds the tree recomputing every annotation through `f`, leaving the
+    /// spans and expression structure untouched.
+    pub fn map_anns<B>(self, f: &mut impl FnMut(A) -> B) -> Annotated<N, B> {
+        let annotation = f(self.annotation);
+        let expr = self.expr.map_children(|child| Box::new((*child).map_anns(f)));
+        Annotated {
+            annotation,
+            span: self.span,
+            expr,
+        }
+    }
+
+    /// Drops the annotations, lowering the tree back into `arena` and returning
+    /// the handle of its root. Spans are preserved.
+    pub fn strip(self, arena: &mut ExprArena<N>) -> ExprId {
+        let span = self.span;
+        let node = self.expr.map_children(|child| (*child).strip(arena));
+        arena.alloc(node, span)
+    }
 }
 
 pub trait NameKind {
@@ -57,7 +150,7 @@ pub trait NameKind {
     type Type;
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub struct SourceAst;
 
 impl NameKind for SourceAst {
@@ -69,7 +162,7 @@ impl NameKind for SourceAst {
     type Type = TypeName;
 }
 
-impl<N> Expr<N>
+impl<N, A> ExprF<N, A>
 where
     N: NameKind,
     N::Reference: Debug,
@@ -79,53 +172,313 @@ where
     N::Member: Debug,
     N::Type: Debug,
 {
-    pub const EMPTY: Self = Expr::Seq(Seq { exprs: vec![] });
+    pub const EMPTY: Self = ExprF::Seq(SeqF { exprs: vec![] });
 
-    pub fn is_empty(&self) -> bool {
+    /// Applies `f` to every child position exactly once, leaving the
+    /// non-recursive payload untouched. This is the single place that knows the
+    /// shape of the functor; [`ExprArena::fold`] and downstream algebras build
+    /// on it.
+    pub fn map_children<B>(self, mut f: impl FnMut(A) -> B) -> ExprF<N, B> {
         match self {
-            Expr::Seq(seq) => seq.exprs.iter().all(|expr| expr.is_empty()),
-            Expr::Goto(target, _) => target.resolved,
-            _ => false,
+            ExprF::Ident(name) => ExprF::Ident(name),
+            ExprF::Constant(value) => ExprF::Constant(value),
+            ExprF::ArrayLit(elems, type_) => ExprF::ArrayLit(elems.into_iter().map(&mut f).collect(), type_),
+            ExprF::InterpolatedString(prefix, parts) => {
+                let parts = parts.into_iter().map(|(expr, str)| (f(expr), str)).collect();
+                ExprF::InterpolatedString(prefix, parts)
+            }
+            ExprF::Declare(local, type_, init) => ExprF::Declare(local, type_, init.map(&mut f)),
+            ExprF::Cast(type_, expr) => ExprF::Cast(type_, f(expr)),
+            ExprF::Assign(lhs, rhs) => ExprF::Assign(f(lhs), f(rhs)),
+            ExprF::Call(callable, type_args, args) => {
+                ExprF::Call(callable, type_args, args.into_iter().map(&mut f).collect())
+            }
+            ExprF::MethodCall(receiver, func, args) => {
+                let receiver = f(receiver);
+                ExprF::MethodCall(receiver, func, args.into_iter().map(&mut f).collect())
+            }
+            ExprF::Member(expr, member) => ExprF::Member(f(expr), member),
+            ExprF::ArrayElem(array, index) => ExprF::ArrayElem(f(array), f(index)),
+            ExprF::New(type_, args) => ExprF::New(type_, args.into_iter().map(&mut f).collect()),
+            ExprF::Return(expr) => ExprF::Return(expr.map(&mut f)),
+            ExprF::Seq(seq) => ExprF::Seq(seq.map_children(&mut f)),
+            ExprF::Switch(scrutinee, cases, default) => {
+                let scrutinee = f(scrutinee);
+                let cases = cases.into_iter().map(|case| case.map_children(&mut f)).collect();
+                ExprF::Switch(scrutinee, cases, default.map(|seq| seq.map_children(&mut f)))
+            }
+            ExprF::Goto(target) => ExprF::Goto(target),
+            ExprF::If(cond, then, otherwise) => {
+                let cond = f(cond);
+                let then = then.map_children(&mut f);
+                ExprF::If(cond, then, otherwise.map(|seq| seq.map_children(&mut f)))
+            }
+            ExprF::Conditional(cond, then, otherwise) => ExprF::Conditional(f(cond), f(then), f(otherwise)),
+            ExprF::While(cond, body) => {
+                let cond = f(cond);
+                ExprF::While(cond, body.map_children(&mut f))
+            }
+            ExprF::ForIn(local, iter, body) => {
+                let iter = f(iter);
+                ExprF::ForIn(local, iter, body.map_children(&mut f))
+            }
+            ExprF::BinOp(lhs, rhs, op) => ExprF::BinOp(f(lhs), f(rhs), op),
+            ExprF::UnOp(expr, op) => ExprF::UnOp(f(expr), op),
+            ExprF::This => ExprF::This,
+            ExprF::Super => ExprF::Super,
+            ExprF::Break => ExprF::Break,
+            ExprF::Null => ExprF::Null,
         }
     }
+}
 
-    pub fn span(&self) -> Span {
+// `Clone` is hand-written rather than derived: a derive would emit a bound-free
+// impl that can't clone the `Name::*` associated-type payloads, so it fails to
+// compile. Keeping the `Clone` bounds here rather than on the `ExprF`
+// definition avoids forcing them onto every use of the functor.
+impl<N, A> Clone for ExprF<N, A>
+where
+    N: NameKind,
+    N::Reference: Debug + Clone,
+    N::Callable: Debug + Clone,
+    N::Local: Debug + Clone,
+    N::Function: Debug + Clone,
+    N::Member: Debug + Clone,
+    N::Type: Debug + Clone,
+    A: Clone,
+{
+    fn clone(&self) -> Self {
         match self {
-            Expr::Ident(_, span) => *span,
-            Expr::Constant(_, span) => *span,
-            Expr::ArrayLit(_, _, span) => *span,
-            Expr::InterpolatedString(_, _, span) => *span,
-            Expr::Declare(_, _, _, span) => *span,
-            Expr::Cast(_, _, span) => *span,
-            Expr::Assign(_, _, span) => *span,
-            Expr::Call(_, _, _, span) => *span,
-            Expr::MethodCall(_, _, _, span) => *span,
-            Expr::Member(_, _, span) => *span,
-            Expr::ArrayElem(_, _, span) => *span,
-            Expr::New(_, _, span) => *span,
-            Expr::Return(_, span) => *span,
-            Expr::Seq(seq) => {
-                let start = seq.exprs.first().map(Self::span).unwrap_or_default();
-                let end = seq.exprs.last().map(Self::span).unwrap_or_default();
-                start.merge(end)
+            ExprF::Ident(name) => ExprF::Ident(name.clone()),
+            ExprF::Constant(value) => ExprF::Constant(value.clone()),
+            ExprF::ArrayLit(elems, type_) => ExprF::ArrayLit(elems.clone(), type_.clone()),
+            ExprF::InterpolatedString(prefix, parts) => ExprF::InterpolatedString(prefix.clone(), parts.clone()),
+            ExprF::Declare(local, type_, init) => ExprF::Declare(local.clone(), type_.clone(), init.clone()),
+            ExprF::Cast(type_, expr) => ExprF::Cast(type_.clone(), expr.clone()),
+            ExprF::Assign(lhs, rhs) => ExprF::Assign(lhs.clone(), rhs.clone()),
+            ExprF::Call(callable, type_args, args) => ExprF::Call(callable.clone(), type_args.clone(), args.clone()),
+            ExprF::MethodCall(receiver, func, args) => ExprF::MethodCall(receiver.clone(), func.clone(), args.clone()),
+            ExprF::Member(expr, member) => ExprF::Member(expr.clone(), member.clone()),
+            ExprF::ArrayElem(array, index) => ExprF::ArrayElem(array.clone(), index.clone()),
+            ExprF::New(type_, args) => ExprF::New(type_.clone(), args.clone()),
+            ExprF::Return(expr) => ExprF::Return(expr.clone()),
+            ExprF::Seq(seq) => ExprF::Seq(seq.clone()),
+            ExprF::Switch(scrutinee, cases, default) => {
+                ExprF::Switch(scrutinee.clone(), cases.clone(), default.clone())
             }
-            Expr::Switch(_, _, _, span) => *span,
-            Expr::Goto(_, span) => *span,
-            Expr::If(_, _, _, span) => *span,
-            Expr::Conditional(_, _, _, span) => *span,
-            Expr::While(_, _, span) => *span,
-            Expr::ForIn(_, _, _, span) => *span,
-            Expr::BinOp(_, _, _, span) => *span,
-            Expr::UnOp(_, _, span) => *span,
-            Expr::This(span) => *span,
-            Expr::Super(span) => *span,
-            Expr::Break(span) => *span,
-            Expr::Null(span) => *span,
+            ExprF::Goto(target) => ExprF::Goto(target.clone()),
+            ExprF::If(cond, then, otherwise) => ExprF::If(cond.clone(), then.clone(), otherwise.clone()),
+            ExprF::Conditional(cond, then, otherwise) => {
+                ExprF::Conditional(cond.clone(), then.clone(), otherwise.clone())
+            }
+            ExprF::While(cond, body) => ExprF::While(cond.clone(), body.clone()),
+            ExprF::ForIn(local, iter, body) => ExprF::ForIn(local.clone(), iter.clone(), body.clone()),
+            ExprF::BinOp(lhs, rhs, op) => ExprF::BinOp(lhs.clone(), rhs.clone(), *op),
+            ExprF::UnOp(expr, op) => ExprF::UnOp(expr.clone(), *op),
+            ExprF::This => ExprF::This,
+            ExprF::Super => ExprF::Super,
+            ExprF::Break => ExprF::Break,
+            ExprF::Null => ExprF::Null,
+        }
+    }
+}
+
+/// A flat arena that owns every [`Expr`] node in a body. Recursive positions
+/// refer to children by [`ExprId`], so the arena is the single source of truth
+/// for node storage; spans live in a parallel side-table rather than in the
+/// nodes themselves.
+#[derive(Debug)]
+pub struct ExprArena<N>
+where
+    N: NameKind,
+    N::Reference: Debug,
+    N::Callable: Debug,
+    N::Local: Debug,
+    N::Function: Debug,
+    N::Member: Debug,
+    N::Type: Debug,
+{
+    exprs: Vec<Expr<N>>,
+    spans: ArenaMap<ExprId, Span>,
+}
+
+impl<N> ExprArena<N>
+where
+    N: NameKind,
+    N::Reference: Debug,
+    N::Callable: Debug,
+    N::Local: Debug,
+    N::Function: Debug,
+    N::Member: Debug,
+    N::Type: Debug,
+{
+    pub fn new() -> Self {
+        ExprArena {
+            exprs: vec![],
+            spans: ArenaMap::new(),
         }
     }
+
+    /// Move a node into the arena together with its source span, returning the
+    /// handle that now refers to it.
+    pub fn alloc(&mut self, expr: Expr<N>, span: Span) -> ExprId {
+        let id = ExprId::from_raw(self.exprs.len() as u32);
+        self.exprs.push(expr);
+        self.spans.insert(id, span);
+        id
+    }
+
+    /// The span recorded for `id` when it was allocated.
+    #[inline]
+    pub fn span(&self, id: ExprId) -> Span {
+        self.spans[id]
+    }
+
+    /// Mutable access to a node, for passes that rewrite the tree in place.
+    #[inline]
+    pub fn node_mut(&mut self, id: ExprId) -> &mut Expr<N> {
+        &mut self.exprs[id.into_raw() as usize]
+    }
+
+    /// Overwrites the span recorded for `id`, e.g. after merging operand spans.
+    #[inline]
+    pub fn set_span(&mut self, id: ExprId, span: Span) {
+        self.spans.set(id, span);
+    }
+
+    /// Moves the node at `id` out of the arena, leaving a [`Expr::Null`]
+    /// tombstone behind. The id must no longer be reachable afterwards.
+    pub fn take(&mut self, id: ExprId) -> Expr<N> {
+        std::mem::replace(self.node_mut(id), Expr::Null)
+    }
+
+    /// Iterates over every id allocated in this arena, in allocation order.
+    pub fn ids(&self) -> impl Iterator<Item = ExprId> + '_ {
+        (0..self.exprs.len() as u32).map(ExprId::from_raw)
+    }
+}
+
+impl<N> ExprArena<N>
+where
+    N: NameKind,
+    N::Reference: Debug + Clone,
+    N::Callable: Debug + Clone,
+    N::Local: Debug + Clone,
+    N::Function: Debug + Clone,
+    N::Member: Debug + Clone,
+    N::Type: Debug + Clone,
+{
+    /// Folds the body rooted at `id` bottom-up: each node's children are folded
+    /// first, then `f` is applied to the resulting [`ExprF`] (its children now
+    /// the fold results) together with the node's span. This is the single
+    /// recursion point; passes express themselves as the algebra `f`.
+    pub fn fold<B>(&self, id: ExprId, f: &mut impl FnMut(ExprF<N, B>, Span) -> B) -> B {
+        let layer = self[id].clone().map_children(|child| self.fold(child, f));
+        f(layer, self.span(id))
+    }
+
+    /// Whether the body is devoid of effectful statements, expressed as a fold:
+    /// a [`Seq`] whose statements are all empty (including one with no
+    /// statements) and a resolved [`Goto`] are empty, everything else is not.
+    pub fn is_empty(&self, id: ExprId) -> bool {
+        self.fold(id, &mut |layer, _| match layer {
+            ExprF::Seq(seq) => seq.exprs.iter().all(|&empty| empty),
+            ExprF::Goto(target) => target.resolved,
+            _ => false,
+        })
+    }
+
+    /// Builds an [`Annotated`] tree for the body rooted at `id`, computing each
+    /// node's annotation from its bare node via `f`. Spans are carried over and
+    /// every child, including control-flow bodies, is visited.
+    pub fn annotate<A>(&self, id: ExprId, f: &mut impl FnMut(&Expr<N>) -> A) -> Annotated<N, A> {
+        let annotation = f(&self[id]);
+        let span = self.span(id);
+        let expr = self[id].clone().map_children(|child| Box::new(self.annotate(child, f)));
+        Annotated { annotation, span, expr }
+    }
+}
+
+impl<N> Default for ExprArena<N>
+where
+    N: NameKind,
+    N::Reference: Debug,
+    N::Callable: Debug,
+    N::Local: Debug,
+    N::Function: Debug,
+    N::Member: Debug,
+    N::Type: Debug,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<N> Index<ExprId> for ExprArena<N>
+where
+    N: NameKind,
+    N::Reference: Debug,
+    N::Callable: Debug,
+    N::Local: Debug,
+    N::Function: Debug,
+    N::Member: Debug,
+    N::Type: Debug,
+{
+    type Output = Expr<N>;
+
+    #[inline]
+    fn index(&self, id: ExprId) -> &Expr<N> {
+        &self.exprs[id.into_raw() as usize]
+    }
+}
+
+/// A dense map from arena handles to some per-node payload, stored parallel to
+/// the arena itself. Used for the span side-table, but generic so later passes
+/// can hang their own data off the same ids.
+#[derive(Debug)]
+pub struct ArenaMap<I, V> {
+    values: Vec<V>,
+    _marker: PhantomData<fn(I)>,
+}
+
+impl<V> ArenaMap<ExprId, V> {
+    pub fn new() -> Self {
+        ArenaMap {
+            values: vec![],
+            _marker: PhantomData,
+        }
+    }
+
+    /// Inserts the value for `id`. Ids are expected to be inserted in
+    /// allocation order, mirroring the arena they index.
+    pub fn insert(&mut self, id: ExprId, value: V) {
+        debug_assert_eq!(id.into_raw() as usize, self.values.len());
+        self.values.push(value);
+    }
+
+    /// Overwrites the value already stored for `id`.
+    pub fn set(&mut self, id: ExprId, value: V) {
+        self.values[id.into_raw() as usize] = value;
+    }
+}
+
+impl<V> Default for ArenaMap<ExprId, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<V> Index<ExprId> for ArenaMap<ExprId, V> {
+    type Output = V;
+
+    #[inline]
+    fn index(&self, id: ExprId) -> &V {
+        &self.values[id.into_raw() as usize]
+    }
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Constant {
     String(Literal, Ref<str>),
     F32(f32),
@@ -310,51 +663,57 @@ pub enum UnOp {
     Neg,
 }
 
-#[derive(Debug)]
-pub struct SwitchCase<N>
-where
-    N: NameKind,
-    N::Reference: Debug,
-    N::Callable: Debug,
-    N::Local: Debug,
-    N::Function: Debug,
-    N::Member: Debug,
-    N::Type: Debug,
-{
-    pub matcher: Expr<N>,
-    pub body: Seq<N>,
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SwitchCaseF<A> {
+    pub matcher: A,
+    pub body: SeqF<A>,
+}
+
+impl<A> SwitchCaseF<A> {
+    /// Maps every child position in the case and its body through `f`.
+    pub fn map_children<B>(self, f: &mut impl FnMut(A) -> B) -> SwitchCaseF<B> {
+        SwitchCaseF {
+            matcher: f(self.matcher),
+            body: self.body.map_children(f),
+        }
+    }
 }
 
-#[derive(Debug)]
-pub struct Seq<N>
-where
-    N: NameKind,
-    N::Reference: Debug,
-    N::Callable: Debug,
-    N::Local: Debug,
-    N::Function: Debug,
-    N::Member: Debug,
-    N::Type: Debug,
-{
-    pub exprs: Vec<Expr<N>>,
+/// A sequence of statements. Kept as its own functor position so control-flow
+/// bodies participate in [`ExprF::map_children`] and the fold.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SeqF<A> {
+    pub exprs: Vec<A>,
 }
 
-impl<N> Seq<N>
-where
-    N: NameKind,
-    N::Reference: Debug,
-    N::Callable: Debug,
-    N::Local: Debug,
-    N::Function: Debug,
-    N::Member: Debug,
-    N::Type: Debug,
-{
-    pub fn new(exprs: Vec<Expr<N>>) -> Seq<N> {
-        Seq { exprs }
+impl<A> SeqF<A> {
+    pub fn new(exprs: Vec<A>) -> SeqF<A> {
+        SeqF { exprs }
+    }
+
+    /// Iterates over the statements in this sequence.
+    pub fn ids(&self) -> slice::Iter<'_, A> {
+        self.exprs.iter()
+    }
+
+    /// Maps every statement through `f`.
+    pub fn map_children<B>(self, f: &mut impl FnMut(A) -> B) -> SeqF<B> {
+        SeqF {
+            exprs: self.exprs.into_iter().map(f).collect(),
+        }
     }
 }
 
+/// A switch case as stored in the arena.
+pub type SwitchCase = SwitchCaseF<ExprId>;
+
+/// A statement sequence as stored in the arena.
+pub type Seq = SeqF<ExprId>;
+
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Literal {
     String,
     Name,
@@ -363,6 +722,7 @@ pub enum Literal {
 }
 
 #[derive(Debug, Default, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Pos(pub u32);
 
 impl Pos {
@@ -405,6 +765,7 @@ impl From<Pos> for usize {
 }
 
 #[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Span {
     pub low: Pos,
     pub high: Pos,
@@ -427,6 +788,7 @@ impl Span {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Target {
     pub position: u16,
     pub resolved: bool,
@@ -441,7 +803,7 @@ impl Target {
     }
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct TypeName {
     name: Ident,
     arguments: Vec<TypeName>,
@@ -576,3 +938,76 @@ pub enum Kind {
     ScriptRef,
     Array,
 }
+
+/// `serde` support for the AST, behind the `serde` feature. Most types derive
+/// the obvious shape; the impls below give the lossless, tool-friendly
+/// representations: operators by their `strum` names, `TypeName` by its
+/// `repr()` (round-tripping through `from_repr`), and `Ident` as a plain
+/// string that always deserializes back into `Ident::Owned`. The feature
+/// enables `serde` with its `rc` feature so the `Ref<str>` payloads serialize
+/// as strings.
+#[cfg(feature = "serde")]
+pub mod serde_support {
+    use std::str::FromStr;
+
+    use serde::de::{Error as _, Unexpected};
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::{BinOp, Ident, TypeName, UnOp};
+    use crate::Ref;
+
+    /// Version of the emitted AST shape. Bump on any breaking change so
+    /// consumers can reject formats they don't understand.
+    pub const FORMAT_VERSION: u32 = 1;
+
+    impl Serialize for Ident {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.serialize_str(self.as_ref())
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Ident {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let str = String::deserialize(deserializer)?;
+            Ok(Ident::Owned(Ref::from(str)))
+        }
+    }
+
+    impl Serialize for TypeName {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.serialize_str(self.repr().as_ref())
+        }
+    }
+
+    impl<'de> Deserialize<'de> for TypeName {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let str = String::deserialize(deserializer)?;
+            Ok(TypeName::from_repr(&str))
+        }
+    }
+
+    /// Serializes an operator as its `strum` string name and parses it back via
+    /// the generated `FromStr`, keeping the wire form independent of the Rust
+    /// variant identifiers.
+    macro_rules! serde_via_strum {
+        ($ty:ty) => {
+            impl Serialize for $ty {
+                fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                    let name: &'static str = (*self).into();
+                    serializer.serialize_str(name)
+                }
+            }
+
+            impl<'de> Deserialize<'de> for $ty {
+                fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                    let str = String::deserialize(deserializer)?;
+                    <$ty>::from_str(&str)
+                        .map_err(|_| D::Error::invalid_value(Unexpected::Str(&str), &stringify!($ty)))
+                }
+            }
+        };
+    }
+
+    serde_via_strum!(BinOp);
+    serde_via_strum!(UnOp);
+}
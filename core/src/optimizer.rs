@@ -0,0 +1,363 @@
+//! A bottom-up constant-folding and algebraic-simplification pass over a
+//! [`SourceAst`] body. It folds pure constant subtrees (`1 + 2` → `3`) and
+//! applies the side-effect-free identity rewrites (`x + 0` → `x`, `x * 0` → `0`,
+//! …), rewriting the [`ExprArena`] in place. Nodes that may have side effects
+//! (`Call`/`MethodCall`/`New`/`Assign`) are never discarded, the assigning
+//! operators are never folded, and division or modulo by a zero literal is
+//! left untouched with a warning.
+
+use crate::ast::{BinOp, Constant, Expr, ExprArena, ExprId, SourceAst, Span, SwitchCase, UnOp};
+
+/// A warning raised while folding, pointing at the offending span.
+#[derive(Debug)]
+pub struct FoldDiagnostic {
+    pub span: Span,
+    pub message: String,
+}
+
+/// Folds every constant subtree reachable from `root`, returning any warnings
+/// collected along the way. The arena is simplified in place.
+pub fn fold_body(arena: &mut ExprArena<SourceAst>, root: ExprId) -> Vec<FoldDiagnostic> {
+    let mut folder = ConstFolder {
+        arena,
+        diagnostics: vec![],
+    };
+    folder.fold(root);
+    folder.diagnostics
+}
+
+struct ConstFolder<'a> {
+    arena: &'a mut ExprArena<SourceAst>,
+    diagnostics: Vec<FoldDiagnostic>,
+}
+
+impl ConstFolder<'_> {
+    fn fold(&mut self, id: ExprId) {
+        for child in child_ids(&self.arena[id]) {
+            self.fold(child);
+        }
+        self.simplify(id);
+    }
+
+    fn simplify(&mut self, id: ExprId) {
+        match &self.arena[id] {
+            Expr::BinOp(lhs, rhs, op) => self.simplify_bin_op(id, *lhs, *rhs, *op),
+            Expr::UnOp(operand, op) => self.simplify_un_op(id, *operand, *op),
+            _ => {}
+        }
+    }
+
+    fn simplify_bin_op(&mut self, id: ExprId, lhs: ExprId, rhs: ExprId, op: BinOp) {
+        // Never touch the assigning operators; they mutate their left operand.
+        if is_assign(op) {
+            return;
+        }
+
+        match (&self.arena[lhs], &self.arena[rhs]) {
+            (Expr::Constant(l), Expr::Constant(r)) => match eval_bin_op(l, r, op) {
+                Eval::Value(value) => self.set_constant(id, value, lhs, rhs),
+                Eval::Overflow(value) => {
+                    self.warn(id, "arithmetic overflow in constant expression, wrapping");
+                    self.set_constant(id, value, lhs, rhs);
+                }
+                Eval::DivByZero => self.warn(id, "division by zero, left unevaluated"),
+                Eval::Skip => {}
+            },
+            _ => self.rewrite_identity(id, lhs, rhs, op),
+        }
+    }
+
+    /// Identity rewrites that hold regardless of the runtime value of the
+    /// surviving operand. An operand is only discarded when it cannot have side
+    /// effects.
+    fn rewrite_identity(&mut self, id: ExprId, lhs: ExprId, rhs: ExprId, op: BinOp) {
+        match op {
+            // `x + 0` / `0 + x` / `x - 0` -> x (the dropped literal is pure).
+            BinOp::Add if self.is_zero(rhs) => self.keep(id, lhs),
+            BinOp::Add if self.is_zero(lhs) => self.keep(id, rhs),
+            BinOp::Subtract if self.is_zero(rhs) => self.keep(id, lhs),
+            // `x * 1` / `1 * x` -> x.
+            BinOp::Multiply if self.is_one(rhs) => self.keep(id, lhs),
+            BinOp::Multiply if self.is_one(lhs) => self.keep(id, rhs),
+            // `x * 0` / `0 * x` -> 0, but only if the dropped operand is pure.
+            BinOp::Multiply if self.is_zero(rhs) && !self.has_side_effects(lhs) => self.keep(id, rhs),
+            BinOp::Multiply if self.is_zero(lhs) && !self.has_side_effects(rhs) => self.keep(id, lhs),
+            // `x || true` -> true, `x && false` -> false; the other operand is
+            // discarded so it must be pure.
+            BinOp::LogicOr if self.is_bool(rhs, true) && !self.has_side_effects(lhs) => self.keep(id, rhs),
+            BinOp::LogicOr if self.is_bool(lhs, true) && !self.has_side_effects(rhs) => self.keep(id, lhs),
+            BinOp::LogicAnd if self.is_bool(rhs, false) && !self.has_side_effects(lhs) => self.keep(id, rhs),
+            BinOp::LogicAnd if self.is_bool(lhs, false) && !self.has_side_effects(rhs) => self.keep(id, lhs),
+            // A zero-literal divisor under a non-constant dividend is left as is,
+            // but reported just like the constant case.
+            BinOp::Divide | BinOp::Modulo
+                if self.is_zero(rhs)
+                    || matches!(&self.arena[rhs], Expr::Constant(Constant::F32(v)) if *v == 0.0)
+                    || matches!(&self.arena[rhs], Expr::Constant(Constant::F64(v)) if *v == 0.0) =>
+            {
+                self.warn(id, "division by zero, left unevaluated")
+            }
+            _ => {}
+        }
+    }
+
+    fn simplify_un_op(&mut self, id: ExprId, operand: ExprId, op: UnOp) {
+        if let Expr::Constant(value) = &self.arena[operand] {
+            if let Some(folded) = eval_un_op(value, op) {
+                let span = self.arena.span(id).merge(self.arena.span(operand));
+                *self.arena.node_mut(id) = Expr::Constant(folded);
+                self.arena.set_span(id, span);
+            }
+        }
+    }
+
+    /// Replaces `id` with the folded constant, merging the operand spans.
+    fn set_constant(&mut self, id: ExprId, value: Constant, lhs: ExprId, rhs: ExprId) {
+        let span = self.arena.span(lhs).merge(self.arena.span(rhs));
+        *self.arena.node_mut(id) = Expr::Constant(value);
+        self.arena.set_span(id, span);
+    }
+
+    /// Replaces `id` with the subtree at `child`, moving the node out of the
+    /// arena so no copy is required.
+    fn keep(&mut self, id: ExprId, child: ExprId) {
+        let node = self.arena.take(child);
+        *self.arena.node_mut(id) = node;
+    }
+
+    fn warn(&mut self, id: ExprId, message: &str) {
+        self.diagnostics.push(FoldDiagnostic {
+            span: self.arena.span(id),
+            message: message.to_owned(),
+        });
+    }
+
+    fn is_zero(&self, id: ExprId) -> bool {
+        matches!(&self.arena[id], Expr::Constant(c) if is_zero(c))
+    }
+
+    fn is_one(&self, id: ExprId) -> bool {
+        matches!(&self.arena[id], Expr::Constant(c) if is_one(c))
+    }
+
+    fn is_bool(&self, id: ExprId, value: bool) -> bool {
+        matches!(&self.arena[id], Expr::Constant(Constant::Bool(b)) if *b == value)
+    }
+
+    /// Whether the subtree may observably mutate state, in which case it must
+    /// not be discarded by a rewrite.
+    fn has_side_effects(&self, id: ExprId) -> bool {
+        let expr = &self.arena[id];
+        let effectful = match expr {
+            Expr::Call(..) | Expr::MethodCall(..) | Expr::New(..) | Expr::Assign(..) => true,
+            // Compound assignments mutate their left operand just like `Assign`.
+            Expr::BinOp(_, _, op) => is_assign(*op),
+            _ => false,
+        };
+        effectful || child_ids(expr).into_iter().any(|child| self.has_side_effects(child))
+    }
+}
+
+fn is_assign(op: BinOp) -> bool {
+    matches!(
+        op,
+        BinOp::AssignAdd
+            | BinOp::AssignSubtract
+            | BinOp::AssignMultiply
+            | BinOp::AssignDivide
+            | BinOp::AssignOr
+            | BinOp::AssignAnd
+    )
+}
+
+// Restricted to integer constants: folding `x + 0`/`x - 0`/`x * 0` on floats
+// would break NaN/inf and signed-zero semantics (e.g. `-0.0 + 0.0 == +0.0`,
+// `NaN * 0.0 == NaN`), so those identities must not fire on `F32`/`F64`.
+fn is_zero(constant: &Constant) -> bool {
+    match constant {
+        Constant::I32(v) => *v == 0,
+        Constant::I64(v) => *v == 0,
+        Constant::U32(v) => *v == 0,
+        Constant::U64(v) => *v == 0,
+        _ => false,
+    }
+}
+
+fn is_one(constant: &Constant) -> bool {
+    match constant {
+        Constant::I32(v) => *v == 1,
+        Constant::I64(v) => *v == 1,
+        Constant::U32(v) => *v == 1,
+        Constant::U64(v) => *v == 1,
+        Constant::F32(v) => *v == 1.0,
+        Constant::F64(v) => *v == 1.0,
+        _ => false,
+    }
+}
+
+/// The outcome of folding a binary operator over two constants.
+enum Eval {
+    Value(Constant),
+    Overflow(Constant),
+    DivByZero,
+    Skip,
+}
+
+fn eval_bin_op(lhs: &Constant, rhs: &Constant, op: BinOp) -> Eval {
+    /// Expands the integer operator table for a single concrete type, wrapping
+    /// on overflow and reporting the wrap, leaving division/modulo by zero for
+    /// the caller to diagnose.
+    macro_rules! int_op {
+        ($l:expr, $r:expr, $ctor:expr) => {{
+            let (l, r) = ($l, $r);
+            match op {
+                BinOp::Add => overflowed(l.overflowing_add(r), $ctor),
+                BinOp::Subtract => overflowed(l.overflowing_sub(r), $ctor),
+                BinOp::Multiply => overflowed(l.overflowing_mul(r), $ctor),
+                BinOp::Divide if r == 0 => Eval::DivByZero,
+                BinOp::Modulo if r == 0 => Eval::DivByZero,
+                BinOp::Divide => Eval::Value($ctor(l.wrapping_div(r))),
+                BinOp::Modulo => Eval::Value($ctor(l.wrapping_rem(r))),
+                BinOp::And => Eval::Value($ctor(l & r)),
+                BinOp::Or => Eval::Value($ctor(l | r)),
+                BinOp::Xor => Eval::Value($ctor(l ^ r)),
+                BinOp::Equal => Eval::Value(Constant::Bool(l == r)),
+                BinOp::NotEqual => Eval::Value(Constant::Bool(l != r)),
+                BinOp::Less => Eval::Value(Constant::Bool(l < r)),
+                BinOp::LessEqual => Eval::Value(Constant::Bool(l <= r)),
+                BinOp::Greater => Eval::Value(Constant::Bool(l > r)),
+                BinOp::GreaterEqual => Eval::Value(Constant::Bool(l >= r)),
+                _ => Eval::Skip,
+            }
+        }};
+    }
+
+    match (lhs, rhs) {
+        (Constant::I32(l), Constant::I32(r)) => int_op!(*l, *r, Constant::I32),
+        (Constant::I64(l), Constant::I64(r)) => int_op!(*l, *r, Constant::I64),
+        (Constant::U32(l), Constant::U32(r)) => int_op!(*l, *r, Constant::U32),
+        (Constant::U64(l), Constant::U64(r)) => int_op!(*l, *r, Constant::U64),
+        (Constant::F32(l), Constant::F32(r)) => float_op(f64::from(*l), f64::from(*r), op, |v| Constant::F32(v as f32)),
+        (Constant::F64(l), Constant::F64(r)) => float_op(*l, *r, op, Constant::F64),
+        (Constant::Bool(l), Constant::Bool(r)) => bool_op(*l, *r, op),
+        _ => Eval::Skip,
+    }
+}
+
+/// Wraps an `overflowing_*` result, flagging the wrap as a diagnostic.
+fn overflowed<T>((value, overflow): (T, bool), ctor: fn(T) -> Constant) -> Eval {
+    if overflow {
+        Eval::Overflow(ctor(value))
+    } else {
+        Eval::Value(ctor(value))
+    }
+}
+
+/// Folds a floating-point operator. Division or modulo by a zero literal is left unfolded with a warning, as on
+/// the integer path, rather than being baked into an inf/NaN constant.
+fn float_op(lhs: f64, rhs: f64, op: BinOp, wrap: fn(f64) -> Constant) -> Eval {
+    match op {
+        BinOp::Add => Eval::Value(wrap(lhs + rhs)),
+        BinOp::Subtract => Eval::Value(wrap(lhs - rhs)),
+        BinOp::Multiply => Eval::Value(wrap(lhs * rhs)),
+        BinOp::Divide if rhs == 0.0 => Eval::DivByZero,
+        BinOp::Modulo if rhs == 0.0 => Eval::DivByZero,
+        BinOp::Divide => Eval::Value(wrap(lhs / rhs)),
+        BinOp::Modulo => Eval::Value(wrap(lhs % rhs)),
+        BinOp::Equal => Eval::Value(Constant::Bool(lhs == rhs)),
+        BinOp::NotEqual => Eval::Value(Constant::Bool(lhs != rhs)),
+        BinOp::Less => Eval::Value(Constant::Bool(lhs < rhs)),
+        BinOp::LessEqual => Eval::Value(Constant::Bool(lhs <= rhs)),
+        BinOp::Greater => Eval::Value(Constant::Bool(lhs > rhs)),
+        BinOp::GreaterEqual => Eval::Value(Constant::Bool(lhs >= rhs)),
+        _ => Eval::Skip,
+    }
+}
+
+fn bool_op(lhs: bool, rhs: bool, op: BinOp) -> Eval {
+    match op {
+        BinOp::LogicOr | BinOp::Or => Eval::Value(Constant::Bool(lhs || rhs)),
+        BinOp::LogicAnd | BinOp::And => Eval::Value(Constant::Bool(lhs && rhs)),
+        BinOp::Xor => Eval::Value(Constant::Bool(lhs ^ rhs)),
+        BinOp::Equal => Eval::Value(Constant::Bool(lhs == rhs)),
+        BinOp::NotEqual => Eval::Value(Constant::Bool(lhs != rhs)),
+        _ => Eval::Skip,
+    }
+}
+
+fn eval_un_op(operand: &Constant, op: UnOp) -> Option<Constant> {
+    match (op, operand) {
+        (UnOp::Neg, Constant::I32(v)) => Some(Constant::I32(v.wrapping_neg())),
+        (UnOp::Neg, Constant::I64(v)) => Some(Constant::I64(v.wrapping_neg())),
+        (UnOp::Neg, Constant::F32(v)) => Some(Constant::F32(-v)),
+        (UnOp::Neg, Constant::F64(v)) => Some(Constant::F64(-v)),
+        (UnOp::BitNot, Constant::I32(v)) => Some(Constant::I32(!v)),
+        (UnOp::BitNot, Constant::I64(v)) => Some(Constant::I64(!v)),
+        (UnOp::BitNot, Constant::U32(v)) => Some(Constant::U32(!v)),
+        (UnOp::BitNot, Constant::U64(v)) => Some(Constant::U64(!v)),
+        (UnOp::LogicNot, Constant::Bool(v)) => Some(Constant::Bool(!v)),
+        _ => None,
+    }
+}
+
+/// The direct child ids of a node, covering control-flow bodies so the fold
+/// reaches every expression in the body.
+fn child_ids(expr: &Expr<SourceAst>) -> Vec<ExprId> {
+    let mut ids = vec![];
+    match expr {
+        Expr::ArrayLit(elems, _) => ids.extend(elems.iter().copied()),
+        Expr::InterpolatedString(_, parts) => ids.extend(parts.iter().map(|(id, _)| *id)),
+        Expr::Declare(_, _, init) => ids.extend(init.iter().copied()),
+        Expr::Cast(_, expr) => ids.push(*expr),
+        Expr::Assign(lhs, rhs) => ids.extend([*lhs, *rhs]),
+        Expr::Call(_, _, args) => ids.extend(args.iter().copied()),
+        Expr::MethodCall(receiver, _, args) => {
+            ids.push(*receiver);
+            ids.extend(args.iter().copied());
+        }
+        Expr::Member(expr, _) => ids.push(*expr),
+        Expr::ArrayElem(array, index) => ids.extend([*array, *index]),
+        Expr::New(_, args) => ids.extend(args.iter().copied()),
+        Expr::Return(expr) => ids.extend(expr.iter().copied()),
+        Expr::Seq(seq) => ids.extend(seq.exprs.iter().copied()),
+        Expr::Switch(scrutinee, cases, default) => {
+            ids.push(*scrutinee);
+            ids.extend(cases.iter().flat_map(case_ids));
+            if let Some(default) = default {
+                ids.extend(default.exprs.iter().copied());
+            }
+        }
+        Expr::If(cond, then, otherwise) => {
+            ids.push(*cond);
+            ids.extend(then.exprs.iter().copied());
+            if let Some(otherwise) = otherwise {
+                ids.extend(otherwise.exprs.iter().copied());
+            }
+        }
+        Expr::Conditional(cond, then, otherwise) => ids.extend([*cond, *then, *otherwise]),
+        Expr::While(cond, body) => {
+            ids.push(*cond);
+            ids.extend(body.exprs.iter().copied());
+        }
+        Expr::ForIn(_, iter, body) => {
+            ids.push(*iter);
+            ids.extend(body.exprs.iter().copied());
+        }
+        Expr::BinOp(lhs, rhs, _) => ids.extend([*lhs, *rhs]),
+        Expr::UnOp(operand, _) => ids.push(*operand),
+        Expr::Ident(_)
+        | Expr::Constant(_)
+        | Expr::Goto(_)
+        | Expr::This
+        | Expr::Super
+        | Expr::Break
+        | Expr::Null => {}
+    }
+    ids
+}
+
+fn case_ids(case: &SwitchCase) -> Vec<ExprId> {
+    let mut ids = vec![case.matcher];
+    ids.extend(case.body.exprs.iter().copied());
+    ids
+}